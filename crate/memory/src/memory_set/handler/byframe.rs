@@ -10,10 +10,11 @@ impl<T: FrameAllocator> MemoryHandler for ByFrame<T> {
         Box::new(self.clone())
     }
 
-    fn map(&self, pt: &mut PageTable, addr: VirtAddr, attr: &MemoryAttr) {
-        let target = self.allocator.alloc().expect("failed to allocate frame");
+    fn map(&self, pt: &mut PageTable, addr: VirtAddr, attr: &MemoryAttr) -> MapResult {
+        let target = alloc_with_oom(|| self.allocator.alloc())?;
         let entry = pt.map(addr, target);
         attr.apply(entry);
+        Ok(())
     }
 
     fn unmap(&self, pt: &mut PageTable, addr: VirtAddr) {
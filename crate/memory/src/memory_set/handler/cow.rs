@@ -0,0 +1,206 @@
+use super::*;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use spin::Mutex;
+
+/// Reference counts shared by every address space that descends from the same
+/// `fork`. Cloning a `CopyOnWrite` handler (see `box_clone`) shares this table,
+/// so mapped frames stay shared until one side writes to them.
+type RefTable = Arc<Mutex<BTreeMap<PhysAddr, usize>>>;
+
+#[derive(Debug, Clone)]
+pub struct CopyOnWrite<T: FrameAllocator> {
+    allocator: T,
+    rc: RefTable,
+    /// Page-sized bounce buffer used to copy a shared frame's contents on a
+    /// write fault, so the copy never lands a 4 KiB array on the fault stack.
+    scratch: Arc<Mutex<Vec<u8>>>,
+}
+
+impl<T: FrameAllocator> MemoryHandler for CopyOnWrite<T> {
+    fn box_clone(&self) -> Box<MemoryHandler> {
+        // Share the same refcount table so a forked space keeps the pages
+        // read-only-shared instead of duplicating them eagerly.
+        Box::new(CopyOnWrite {
+            allocator: self.allocator.clone(),
+            rc: self.rc.clone(),
+            scratch: self.scratch.clone(),
+        })
+    }
+
+    fn map(&self, pt: &mut PageTable, addr: VirtAddr, attr: &MemoryAttr) -> MapResult {
+        let target = alloc_with_oom(|| self.allocator.alloc())?;
+        let entry = pt.map(addr, target);
+        attr.apply(entry);
+        // A writable area is mapped copy-on-write: record the shared bit and
+        // drop the hardware write bit so the first write traps. A genuinely
+        // read-only area keeps its attributes and is never captured here, so a
+        // write to it still faults through to the kernel.
+        if entry.writable() {
+            entry.set_shared(true);
+            entry.set_writable(false);
+        }
+        entry.update();
+        self.inc_ref(target);
+        Ok(())
+    }
+
+    fn unmap(&self, pt: &mut PageTable, addr: VirtAddr) {
+        let target = pt.get_entry(addr).expect("fail to get entry").target();
+        if self.dec_ref(target) == 0 {
+            self.allocator.dealloc(target);
+        }
+        pt.unmap(addr);
+    }
+
+    fn handle_page_fault(&self, pt: &mut PageTable, addr: VirtAddr) -> bool {
+        // Only a page we explicitly marked copy-on-write is ours to resolve.
+        // A write to a genuinely read-only page is not shared-writable, so it
+        // falls through and the kernel raises a real protection fault.
+        let target = match pt.get_entry(addr) {
+            Some(entry) if entry.present() && entry.writable_shared() => entry.target(),
+            _ => return false,
+        };
+        if self.ref_count(target) > 1 {
+            // Still shared: give this space a private, writable copy.
+            let frame = match alloc_with_oom(|| self.allocator.alloc()).ok() {
+                Some(frame) => frame,
+                None => return false,
+            };
+            // Stage the old contents through the preallocated scratch buffer
+            // rather than a page-sized array on the fault stack.
+            let mut scratch = self.scratch.lock();
+            scratch.copy_from_slice(pt.get_page_slice_mut(addr));
+            // Repoint the existing entry so the area's protection bits survive,
+            // then restore write permission and drop the shared marker.
+            let entry = pt.get_entry(addr).expect("fail to get entry");
+            entry.set_target(frame);
+            entry.clear_shared();
+            entry.set_writable(true);
+            entry.update();
+            pt.flush(addr);
+            pt.get_page_slice_mut(addr).copy_from_slice(&scratch);
+            self.dec_ref(target);
+            self.inc_ref(frame);
+        } else {
+            // Sole owner: just reclaim write permission in place.
+            let entry = pt.get_entry(addr).expect("fail to get entry");
+            entry.clear_shared();
+            entry.set_writable(true);
+            entry.update();
+            pt.flush(addr);
+        }
+        true
+    }
+}
+
+impl<T: FrameAllocator> CopyOnWrite<T> {
+    pub fn new(allocator: T) -> Self {
+        CopyOnWrite {
+            allocator,
+            rc: Arc::new(Mutex::new(BTreeMap::new())),
+            scratch: Arc::new(Mutex::new(vec![0u8; PAGE_SIZE])),
+        }
+    }
+
+    /// Map the frame backing `addr` in `src` into `dst` as a shared copy,
+    /// the core of `fork`: both sides point at one physical frame, both drop
+    /// write permission, and the shared refcount is bumped so the next write
+    /// on either side triggers the copy in `handle_page_fault`.
+    pub fn clone_map(
+        &self,
+        dst: &mut PageTable,
+        src: &mut PageTable,
+        addr: VirtAddr,
+        attr: &MemoryAttr,
+    ) {
+        let target = src.get_entry(addr).expect("fail to get entry").target();
+        // Revoke write on the parent too, otherwise it could mutate the page
+        // the child still shares. Only a writable page becomes copy-on-write;
+        // a read-only page stays read-only and simply shares the frame.
+        let src_entry = src.get_entry(addr).expect("fail to get entry");
+        if src_entry.writable() || src_entry.writable_shared() {
+            src_entry.set_shared(true);
+            src_entry.set_writable(false);
+            src_entry.update();
+        }
+        let entry = dst.map(addr, target);
+        attr.apply(entry);
+        if entry.writable() {
+            entry.set_shared(true);
+            entry.set_writable(false);
+        }
+        entry.update();
+        self.inc_ref(target);
+    }
+
+    fn inc_ref(&self, target: PhysAddr) {
+        *self.rc.lock().entry(target).or_insert(0) += 1;
+    }
+
+    fn dec_ref(&self, target: PhysAddr) -> usize {
+        let mut rc = self.rc.lock();
+        let count = rc.get_mut(&target).map(|c| {
+            *c -= 1;
+            *c
+        });
+        match count {
+            Some(0) | None => {
+                rc.remove(&target);
+                0
+            }
+            Some(n) => n,
+        }
+    }
+
+    fn ref_count(&self, target: PhysAddr) -> usize {
+        self.rc.lock().get(&target).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Default)]
+    struct DummyAllocator;
+
+    impl FrameAllocator for DummyAllocator {
+        fn alloc(&self) -> Option<PhysAddr> {
+            Some(0)
+        }
+        fn dealloc(&self, _target: PhysAddr) {}
+    }
+
+    fn cow() -> CopyOnWrite<DummyAllocator> {
+        CopyOnWrite::new(DummyAllocator)
+    }
+
+    #[test]
+    fn refcount_inc_dec_transitions() {
+        let c = cow();
+        c.inc_ref(0x1000);
+        assert_eq!(c.ref_count(0x1000), 1);
+        c.inc_ref(0x1000);
+        assert_eq!(c.ref_count(0x1000), 2);
+        // dec returns the remaining count; frame is freed only at 0.
+        assert_eq!(c.dec_ref(0x1000), 1);
+        assert_eq!(c.dec_ref(0x1000), 0);
+        assert_eq!(c.ref_count(0x1000), 0);
+    }
+
+    #[test]
+    fn clone_shares_refcount_table() {
+        let parent = cow();
+        parent.inc_ref(0x2000);
+        let child = parent.clone();
+        // A refcount bump through the child is visible to the parent: the
+        // clone shares one refcount table with it.
+        child.inc_ref(0x2000);
+        assert_eq!(parent.ref_count(0x2000), 2);
+        assert_eq!(parent.dec_ref(0x2000), 1);
+        assert_eq!(child.ref_count(0x2000), 1);
+    }
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+#[derive(Debug, Clone)]
+pub struct Delay<T: FrameAllocator> {
+    allocator: T,
+}
+
+impl<T: FrameAllocator> MemoryHandler for Delay<T> {
+    fn box_clone(&self) -> Box<MemoryHandler> {
+        Box::new(self.clone())
+    }
+
+    fn map(&self, pt: &mut PageTable, addr: VirtAddr, attr: &MemoryAttr) -> MapResult {
+        // Honor the area's attributes now and stamp them into the PTE, then
+        // clear the present bit. The bits persist while the page is reserved,
+        // so the fault path only has to install a frame and flip present.
+        let entry = pt.map(addr, 0);
+        attr.apply(entry);
+        entry.set_present(false);
+        entry.update();
+        Ok(())
+    }
+
+    fn unmap(&self, pt: &mut PageTable, addr: VirtAddr) {
+        let entry = pt.get_entry(addr).expect("fail to get entry");
+        if entry.present() {
+            let target = entry.target();
+            self.allocator.dealloc(target);
+        }
+        pt.unmap(addr);
+    }
+
+    fn handle_page_fault(&self, pt: &mut PageTable, addr: VirtAddr) -> bool {
+        let entry = match pt.get_entry(addr) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        if entry.present() {
+            // not reserved by this handler
+            return false;
+        }
+        // Consult the OOM hook instead of panicking: if no frame can be had,
+        // escalate by reporting the fault as unhandled.
+        let target = match alloc_with_oom(|| self.allocator.alloc()).ok() {
+            Some(target) => target,
+            None => return false,
+        };
+        entry.set_target(target);
+        entry.set_present(true);
+        entry.update();
+        let data = pt.get_page_slice_mut(addr);
+        for elem in data.iter_mut() {
+            *elem = 0;
+        }
+        pt.flush(addr);
+        true
+    }
+}
+
+impl<T: FrameAllocator> Delay<T> {
+    pub fn new(allocator: T) -> Self {
+        Delay { allocator }
+    }
+}
@@ -0,0 +1,100 @@
+use super::*;
+use spin::Mutex;
+
+/// Error returned when a `MemoryHandler` cannot complete a mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapError {
+    /// The backing `FrameAllocator` is exhausted.
+    OutOfMemory,
+}
+
+pub type MapResult = Result<(), MapError>;
+
+/// What the caller should do after the out-of-memory handler has run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OomAction {
+    /// Memory was reclaimed; retry the mapping.
+    Retry,
+    /// Nothing could be freed; propagate the error (e.g. `ENOMEM`).
+    Fail,
+}
+
+pub type OomHandler = fn(MapError) -> OomAction;
+
+fn default_oom_handler(_error: MapError) -> OomAction {
+    OomAction::Fail
+}
+
+static OOM_HANDLER: Mutex<OomHandler> = Mutex::new(default_oom_handler);
+
+/// Register the global out-of-memory handler, returning the previous one.
+///
+/// The handler is consulted by callers whose mapping failed so they can
+/// reclaim frames, kill the offending process, or surface `ENOMEM` instead
+/// of aborting the kernel.
+pub fn set_oom_handler(handler: OomHandler) -> OomHandler {
+    core::mem::replace(&mut *OOM_HANDLER.lock(), handler)
+}
+
+/// Run the registered out-of-memory handler for `error`.
+pub fn oom(error: MapError) -> OomAction {
+    let handler = *OOM_HANDLER.lock();
+    handler(error)
+}
+
+/// Allocate a frame, consulting the OOM handler on exhaustion.
+///
+/// On a failed `alloc` the registered handler is given a chance to reclaim
+/// frames; while it answers `Retry` the allocation is attempted again, so a
+/// handler that evicts or kills a process lets the mapping proceed instead of
+/// aborting. The error is only propagated once the handler gives up with
+/// `Fail`.
+pub fn alloc_with_oom<F: Fn() -> Option<PhysAddr>>(alloc: F) -> Result<PhysAddr, MapError> {
+    loop {
+        if let Some(frame) = alloc() {
+            return Ok(frame);
+        }
+        match oom(MapError::OutOfMemory) {
+            OomAction::Retry => continue,
+            OomAction::Fail => return Err(MapError::OutOfMemory),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    fn fail_handler_propagates() {
+        set_oom_handler(default_oom_handler);
+        let err = alloc_with_oom(|| None).unwrap_err();
+        assert_eq!(err, MapError::OutOfMemory);
+    }
+
+    #[test]
+    fn retry_handler_loops_until_reclaimed() {
+        fn retry_once(_: MapError) -> OomAction {
+            if ATTEMPTS.fetch_add(1, Ordering::SeqCst) == 0 {
+                OomAction::Retry
+            } else {
+                OomAction::Fail
+            }
+        }
+        ATTEMPTS.store(0, Ordering::SeqCst);
+        let prev = set_oom_handler(retry_once);
+        // First alloc fails -> Retry, second alloc succeeds.
+        let frame = alloc_with_oom(|| {
+            if ATTEMPTS.load(Ordering::SeqCst) == 1 {
+                Some(0x1000)
+            } else {
+                None
+            }
+        });
+        assert_eq!(frame, Ok(0x1000));
+        set_oom_handler(prev);
+    }
+}
@@ -0,0 +1,170 @@
+use super::*;
+
+/// A slot on a backing store that can hold the contents of one page.
+pub type SlotId = usize;
+
+/// Backing store for evicted pages, typically a disk partition.
+pub trait SwapDevice: Debug + Clone + 'static {
+    /// Reserve a free slot, or `None` when the device is full.
+    fn alloc_slot(&self) -> Option<SlotId>;
+    /// Release a slot previously returned by `alloc_slot`.
+    fn free_slot(&self, slot: SlotId);
+    /// Read the page stored in `slot` into `buf`.
+    fn read_page(&self, slot: SlotId, buf: &mut [u8]);
+    /// Write `buf` into `slot`.
+    fn write_page(&self, slot: SlotId, buf: &[u8]);
+}
+
+#[derive(Debug, Clone)]
+pub struct Swap<T: FrameAllocator, S: SwapDevice> {
+    allocator: T,
+    device: S,
+}
+
+impl<T: FrameAllocator, S: SwapDevice> MemoryHandler for Swap<T, S> {
+    fn box_clone(&self) -> Box<MemoryHandler> {
+        Box::new(self.clone())
+    }
+
+    fn map(&self, pt: &mut PageTable, addr: VirtAddr, attr: &MemoryAttr) -> MapResult {
+        let target = alloc_with_oom(|| self.allocator.alloc())?;
+        let entry = pt.map(addr, target);
+        attr.apply(entry);
+        Ok(())
+    }
+
+    fn unmap(&self, pt: &mut PageTable, addr: VirtAddr) {
+        let entry = pt.get_entry(addr).expect("fail to get entry");
+        if entry.swapped() {
+            self.device.free_slot(entry.target());
+        } else {
+            self.allocator.dealloc(entry.target());
+        }
+        pt.unmap(addr);
+    }
+
+    fn handle_page_fault(&self, pt: &mut PageTable, addr: VirtAddr) -> bool {
+        let slot = match pt.get_entry(addr) {
+            Some(entry) if entry.swapped() => entry.target(),
+            _ => return false,
+        };
+        // Consult the OOM hook rather than panicking when frames are gone;
+        // an unhandled fault lets the kernel escalate or kill the process.
+        let frame = match alloc_with_oom(|| self.allocator.alloc()).ok() {
+            Some(frame) => frame,
+            None => return false,
+        };
+        // Mutate the existing entry in place instead of re-mapping, so the
+        // protection bits (user/execute/read-only) stamped at `map` time and
+        // retained across eviction survive the swap-in.
+        let entry = pt.get_entry(addr).expect("fail to get entry");
+        entry.set_target(frame);
+        entry.set_swapped(false);
+        entry.set_present(true);
+        entry.update();
+        self.device.read_page(slot, pt.get_page_slice_mut(addr));
+        self.device.free_slot(slot);
+        pt.flush(addr);
+        true
+    }
+}
+
+impl<T: FrameAllocator, S: SwapDevice> Swap<T, S> {
+    pub fn new(allocator: T, device: S) -> Self {
+        Swap { allocator, device }
+    }
+
+    /// Evict one page from `candidates` using the clock (second-chance)
+    /// algorithm: skip and clear pages whose accessed bit is set, and evict
+    /// the first page found without it. Returns `true` if a page was freed.
+    pub fn reclaim(&self, pt: &mut PageTable, candidates: &[VirtAddr]) -> bool {
+        // Two sweeps at most: the first clears accessed bits, so a page that
+        // survives it untouched is guaranteed to be evicted on the second.
+        for _ in 0..2 {
+            for &addr in candidates {
+                let entry = match pt.get_entry(addr) {
+                    Some(entry) if entry.present() => entry,
+                    _ => continue,
+                };
+                if entry.accessed() {
+                    entry.clear_accessed();
+                    entry.update();
+                    continue;
+                }
+                return self.swap_out(pt, addr);
+            }
+        }
+        false
+    }
+
+    fn swap_out(&self, pt: &mut PageTable, addr: VirtAddr) -> bool {
+        let slot = match self.device.alloc_slot() {
+            Some(slot) => slot,
+            None => return false,
+        };
+        let frame = pt.get_entry(addr).expect("fail to get entry").target();
+        self.device.write_page(slot, pt.get_page_slice_mut(addr));
+        let entry = pt.get_entry(addr).expect("fail to get entry");
+        entry.set_target(slot);
+        entry.set_present(false);
+        entry.set_swapped(true);
+        entry.update();
+        self.allocator.dealloc(frame);
+        pt.flush(addr);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use spin::Mutex;
+
+    /// In-memory swap device: one `Vec<u8>` page per slot, with a free list.
+    #[derive(Debug, Clone, Default)]
+    struct RamDevice {
+        slots: Arc<Mutex<Vec<Option<Vec<u8>>>>>,
+    }
+
+    impl SwapDevice for RamDevice {
+        fn alloc_slot(&self) -> Option<SlotId> {
+            let mut slots = self.slots.lock();
+            slots.push(Some(vec![0u8; PAGE_SIZE]));
+            Some(slots.len() - 1)
+        }
+        fn free_slot(&self, slot: SlotId) {
+            self.slots.lock()[slot] = None;
+        }
+        fn read_page(&self, slot: SlotId, buf: &mut [u8]) {
+            let slots = self.slots.lock();
+            buf.copy_from_slice(slots[slot].as_ref().expect("read freed slot"));
+        }
+        fn write_page(&self, slot: SlotId, buf: &[u8]) {
+            let mut slots = self.slots.lock();
+            slots[slot].as_mut().expect("write freed slot").copy_from_slice(buf);
+        }
+    }
+
+    #[test]
+    fn device_write_read_round_trip() {
+        let dev = RamDevice::default();
+        let slot = dev.alloc_slot().unwrap();
+        let written = vec![0xABu8; PAGE_SIZE];
+        dev.write_page(slot, &written);
+        let mut read_back = vec![0u8; PAGE_SIZE];
+        dev.read_page(slot, &mut read_back);
+        assert_eq!(written, read_back);
+        dev.free_slot(slot);
+    }
+
+    #[test]
+    fn allocated_slots_are_distinct() {
+        let dev = RamDevice::default();
+        let a = dev.alloc_slot().unwrap();
+        let b = dev.alloc_slot().unwrap();
+        assert_ne!(a, b);
+    }
+}